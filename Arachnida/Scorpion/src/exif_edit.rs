@@ -0,0 +1,546 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+const SOI: [u8; 2] = [0xFF, 0xD8];
+const APP1_MARKER: u8 = 0xE1;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// Tag numbers of IFD0 entries that point to another IFD rather than
+/// holding a value directly: their 4-byte payload is an absolute file
+/// offset that has to be relocated along with everything else.
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_INTEROP_IFD: u16 = 0xA005;
+
+/// IFD1 thumbnail pointer: an absolute file offset stored inline as a LONG.
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+
+/// ASCII IFD0 tags this editor knows how to write. `--set`/`--delete` on
+/// anything else is rejected rather than silently doing nothing.
+const WRITABLE_TAGS: &[(&str, u16)] = &[
+  ("ImageDescription", 0x010E),
+  ("Make", 0x010F),
+  ("Model", 0x0110),
+  ("Software", 0x0131),
+  ("DateTime", 0x0132),
+  ("Artist", 0x013B),
+];
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+  Set(String, String),
+  Delete(String),
+}
+
+pub fn parse_set(arg: &str) -> Result<Edit, Error> {
+  let (tag, value) = arg.split_once('=')
+    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "--set expects TAG=VALUE"))?;
+  Ok(Edit::Set(tag.to_string(), value.to_string()))
+}
+
+pub fn parse_delete(tag: &str) -> Edit {
+  Edit::Delete(tag.to_string())
+}
+
+/// Removes every APPn metadata segment (EXIF, IPTC, XMP, GPS...) from a JPEG
+/// by rewriting its segment list at the byte level. The compressed image
+/// data is never touched, so there's no re-encode quality loss.
+pub fn strip_metadata(path: &Path) -> Result<Vec<u8>, Error> {
+  let data = fs::read(path)?;
+  rewrite_segments(&data, |marker, segment| {
+    if (0xE0..=0xEF).contains(&marker) {
+      None
+    } else {
+      Some(segment.to_vec())
+    }
+  })
+}
+
+/// Applies `edits` to the JPEG at `path` and returns the rewritten bytes.
+///
+/// If the file already carries an Exif TIFF blob, it's patched in place:
+/// existing entries (GPS, `DateTimeOriginal`, `Orientation`, anything else
+/// untouched by `edits`) keep their original bytes, since only the
+/// specific tags named in `edits` are mutated. A tag being set that isn't
+/// already present is inserted by growing IFD0 and relocating the IFD,
+/// out-of-line value and thumbnail offsets that pointed past the insertion
+/// point (strip offsets and offsets inside a MakerNote's own structure are
+/// not relocated); a tag being deleted
+/// has its tag number zeroed out (0 isn't a defined TIFF tag, so compliant
+/// readers just skip it) rather than shifting the whole file to remove it.
+/// If there's no existing Exif blob, a fresh minimal one is built from
+/// scratch containing only the edited ASCII fields.
+pub fn apply_edits(path: &Path, edits: &[Edit]) -> Result<Vec<u8>, Error> {
+  let data = fs::read(path)?;
+
+  let new_app1 = match find_existing_tiff(&data)? {
+    Some(mut tiff) => {
+      apply_edits_to_tiff(&mut tiff, edits)?;
+      build_app1(&tiff)
+    }
+    None => {
+      let mut fields = BTreeMap::new();
+      for edit in edits {
+        if let Edit::Set(name, value) = edit {
+          fields.insert(tag_id(name)?, value.clone());
+        } else if let Edit::Delete(name) = edit {
+          tag_id(name)?; // validate the name even though there's nothing to remove
+        }
+      }
+      build_app1(&build_minimal_tiff(&fields))
+    }
+  };
+
+  let mut inserted = false;
+  let mut out = rewrite_segments(&data, |marker, segment| {
+    let is_exif_app1 = marker == APP1_MARKER && segment.get(4..10) == Some(EXIF_HEADER);
+    if is_exif_app1 {
+      inserted = true;
+      Some(new_app1.clone())
+    } else {
+      Some(segment.to_vec())
+    }
+  })?;
+
+  if !inserted {
+    // No pre-existing Exif APP1 to replace: splice a fresh one in right
+    // after the SOI marker.
+    out.splice(2..2, new_app1);
+  }
+
+  Ok(out)
+}
+
+fn tag_id(name: &str) -> Result<u16, Error> {
+  WRITABLE_TAGS.iter().find(|(n, _)| *n == name).map(|(_, id)| *id)
+    .ok_or_else(|| Error::new(
+      ErrorKind::InvalidInput,
+      format!("Unsupported tag '{}': writable tags are {}", name,
+        WRITABLE_TAGS.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")),
+    ))
+}
+
+/// Applies `edits` directly to the bytes of an existing Exif TIFF blob,
+/// touching only the IFD0 entries named by `edits` and leaving everything
+/// else (including GPS/Exif sub-IFDs and their data) byte-for-byte as it
+/// was.
+fn apply_edits_to_tiff(tiff: &mut Vec<u8>, edits: &[Edit]) -> Result<(), Error> {
+  if tiff.len() < 8 {
+    return Err(Error::new(ErrorKind::InvalidData, "Truncated TIFF header"));
+  }
+  let be = match &tiff[0..2] {
+    b"II" => false,
+    b"MM" => true,
+    _ => return Err(Error::new(ErrorKind::InvalidData, "Invalid TIFF byte order marker")),
+  };
+  let ifd0_offset = read_u32(tiff, 4, be) as usize;
+  read_ifd_count(tiff, ifd0_offset, be)?; // reject a corrupt/out-of-range IFD0 offset up front
+
+  for edit in edits {
+    match edit {
+      Edit::Set(name, value) => {
+        let tag = tag_id(name)?;
+        let mut bytes = value.clone().into_bytes();
+        bytes.push(0); // NUL-terminated, per the TIFF ASCII type
+        set_ascii_entry(tiff, be, ifd0_offset, tag, &bytes)?;
+      }
+      Edit::Delete(name) => {
+        let tag = tag_id(name)?;
+        delete_entry(tiff, be, ifd0_offset, tag)?;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Writes `value` (ASCII type, already NUL-terminated) into the IFD0 entry
+/// for `tag`, patching an existing entry in place or, if none exists yet,
+/// inserting a new one and relocating every offset past the insertion
+/// point.
+fn set_ascii_entry(tiff: &mut Vec<u8>, be: bool, ifd0_offset: usize, tag: u16, value: &[u8]) -> Result<(), Error> {
+  match find_entry(tiff, be, ifd0_offset, tag)? {
+    Some(entry_pos) => write_entry_value(tiff, be, entry_pos, 2, value),
+    None => insert_entry(tiff, be, ifd0_offset, tag, 2, value)?,
+  }
+  Ok(())
+}
+
+/// "Deletes" an existing entry by zeroing its tag number: 0 isn't a
+/// defined TIFF tag, so a compliant reader just skips it, and nothing else
+/// in the file has to move.
+fn delete_entry(tiff: &mut [u8], be: bool, ifd0_offset: usize, tag: u16) -> Result<(), Error> {
+  if let Some(entry_pos) = find_entry(tiff, be, ifd0_offset, tag)? {
+    write_u16(tiff, entry_pos, 0, be);
+  }
+  Ok(())
+}
+
+/// Reads an IFD's entry count, bounds-checked so a corrupted or
+/// attacker-controlled IFD offset (e.g. from an untrusted image) is
+/// rejected with a clean error instead of indexing past the buffer.
+fn read_ifd_count(tiff: &[u8], ifd_offset: usize, be: bool) -> Result<usize, Error> {
+  if ifd_offset + 2 > tiff.len() {
+    return Err(Error::new(ErrorKind::InvalidData, "Malformed TIFF: IFD entry count out of bounds"));
+  }
+  Ok(read_u16(tiff, ifd_offset, be) as usize)
+}
+
+fn find_entry(tiff: &[u8], be: bool, ifd_offset: usize, tag: u16) -> Result<Option<usize>, Error> {
+  let count = read_ifd_count(tiff, ifd_offset, be)?;
+  for i in 0..count {
+    let entry_pos = ifd_offset + 2 + 12 * i;
+    if entry_pos + 12 > tiff.len() {
+      return Err(Error::new(ErrorKind::InvalidData, "Malformed TIFF: IFD entry out of bounds"));
+    }
+    if read_u16(tiff, entry_pos, be) == tag {
+      return Ok(Some(entry_pos));
+    }
+  }
+  Ok(None)
+}
+
+/// Overwrites an existing entry's type/count/value in place. A value that
+/// fits in the 4-byte value field is stored inline; a longer one is
+/// appended at the end of the file and the entry's value field becomes an
+/// offset to it. Either way the entry stays at the same position, so no
+/// other offset in the file needs to change.
+fn write_entry_value(tiff: &mut Vec<u8>, be: bool, entry_pos: usize, type_id: u16, bytes: &[u8]) {
+  write_u16(tiff, entry_pos + 2, type_id, be);
+  write_u32(tiff, entry_pos + 4, bytes.len() as u32, be);
+
+  if bytes.len() <= 4 {
+    let mut inline = [0u8; 4];
+    inline[..bytes.len()].copy_from_slice(bytes);
+    tiff[entry_pos + 8..entry_pos + 12].copy_from_slice(&inline);
+  } else {
+    let offset = tiff.len() as u32;
+    write_u32(tiff, entry_pos + 8, offset, be);
+    tiff.extend_from_slice(bytes);
+  }
+}
+
+/// Inserts a brand new 12-byte IFD0 entry for `tag` at its sorted position,
+/// growing the entries array and relocating the absolute file offsets
+/// elsewhere in the TIFF (sub-IFD pointers, other entries' overflow
+/// offsets, the thumbnail pointer, IFD chain links) that pointed past the
+/// insertion point.
+fn insert_entry(tiff: &mut Vec<u8>, be: bool, ifd0_offset: usize, tag: u16, type_id: u16, bytes: &[u8]) -> Result<(), Error> {
+  let count = read_ifd_count(tiff, ifd0_offset, be)?;
+
+  let mut insert_index = count;
+  for i in 0..count {
+    let entry_pos = ifd0_offset + 2 + 12 * i;
+    if entry_pos + 12 > tiff.len() {
+      return Err(Error::new(ErrorKind::InvalidData, "Malformed TIFF: IFD entry out of bounds"));
+    }
+    if read_u16(tiff, entry_pos, be) > tag {
+      insert_index = i;
+      break;
+    }
+  }
+
+  let insertion_point = ifd0_offset + 2 + 12 * insert_index;
+
+  // Placeholder value (0) so the relocation pass below can't mistake it for
+  // a real offset; the real value is written in afterwards.
+  let mut placeholder = vec![0u8; 12];
+  write_u16(&mut placeholder, 0, tag, be);
+  write_u16(&mut placeholder, 2, type_id, be);
+  write_u32(&mut placeholder, 4, bytes.len() as u32, be);
+  tiff.splice(insertion_point..insertion_point, placeholder);
+
+  write_u16(tiff, ifd0_offset, (count + 1) as u16, be);
+  relocate_offsets_past(tiff, be, ifd0_offset, insertion_point, 12);
+
+  if bytes.len() <= 4 {
+    let mut inline = [0u8; 4];
+    inline[..bytes.len()].copy_from_slice(bytes);
+    tiff[insertion_point + 8..insertion_point + 12].copy_from_slice(&inline);
+  } else {
+    let offset = tiff.len() as u32;
+    write_u32(tiff, insertion_point + 8, offset, be);
+    tiff.extend_from_slice(bytes);
+  }
+
+  Ok(())
+}
+
+/// Walks the IFD chain starting at `ifd_offset` (recursing into Exif/GPS/
+/// Interop sub-IFDs), adding `delta` to every absolute offset field whose
+/// current value is `>= insertion_point`. Used after splicing bytes into
+/// the file to keep every other offset pointing at the right place.
+fn relocate_offsets_past(tiff: &mut [u8], be: bool, ifd_offset: usize, insertion_point: usize, delta: u32) {
+  if ifd_offset + 2 > tiff.len() {
+    return;
+  }
+  let count = read_u16(tiff, ifd_offset, be) as usize;
+
+  for i in 0..count {
+    let entry_pos = ifd_offset + 2 + 12 * i;
+    if entry_pos + 12 > tiff.len() {
+      break;
+    }
+
+    let tag = read_u16(tiff, entry_pos, be);
+    let type_id = read_u16(tiff, entry_pos + 2, be);
+    let count_field = read_u32(tiff, entry_pos + 4, be);
+
+    if tag == TAG_EXIF_IFD || tag == TAG_GPS_IFD || tag == TAG_INTEROP_IFD {
+      bump_offset_if_past(tiff, entry_pos + 8, insertion_point, delta, be);
+      let sub_ifd_offset = read_u32(tiff, entry_pos + 8, be) as usize;
+      relocate_offsets_past(tiff, be, sub_ifd_offset, insertion_point, delta);
+    } else if tag == TAG_JPEG_INTERCHANGE_FORMAT || entry_data_size(type_id, count_field) > 4 {
+      bump_offset_if_past(tiff, entry_pos + 8, insertion_point, delta, be);
+    }
+  }
+
+  let next_ifd_field = ifd_offset + 2 + 12 * count;
+  if next_ifd_field + 4 <= tiff.len() {
+    bump_offset_if_past(tiff, next_ifd_field, insertion_point, delta, be);
+    let next_ifd = read_u32(tiff, next_ifd_field, be) as usize;
+    if next_ifd != 0 {
+      relocate_offsets_past(tiff, be, next_ifd, insertion_point, delta);
+    }
+  }
+}
+
+fn bump_offset_if_past(tiff: &mut [u8], field_pos: usize, insertion_point: usize, delta: u32, be: bool) {
+  let value = read_u32(tiff, field_pos, be) as usize;
+  if value >= insertion_point {
+    write_u32(tiff, field_pos, value as u32 + delta, be);
+  }
+}
+
+/// Byte size of a single component of TIFF type `type_id`; unknown types
+/// are treated as 4 bytes (inline-sized) so relocation never mistakes an
+/// unrecognized value for an out-of-line offset it doesn't understand.
+fn type_size(type_id: u16) -> usize {
+  match type_id {
+    1 | 2 | 6 | 7 => 1,
+    3 | 8 => 2,
+    4 | 9 | 11 => 4,
+    5 | 10 | 12 => 8,
+    _ => 4,
+  }
+}
+
+fn entry_data_size(type_id: u16, count: u32) -> usize {
+  type_size(type_id) * count as usize
+}
+
+fn read_u16(data: &[u8], pos: usize, be: bool) -> u16 {
+  let bytes = [data[pos], data[pos + 1]];
+  if be { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+}
+
+fn read_u32(data: &[u8], pos: usize, be: bool) -> u32 {
+  let bytes = [data[pos], data[pos + 1], data[pos + 2], data[pos + 3]];
+  if be { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+}
+
+fn write_u16(data: &mut [u8], pos: usize, value: u16, be: bool) {
+  let bytes = if be { value.to_be_bytes() } else { value.to_le_bytes() };
+  data[pos..pos + 2].copy_from_slice(&bytes);
+}
+
+fn write_u32(data: &mut [u8], pos: usize, value: u32, be: bool) {
+  let bytes = if be { value.to_be_bytes() } else { value.to_le_bytes() };
+  data[pos..pos + 4].copy_from_slice(&bytes);
+}
+
+/// Locates the first Exif APP1 segment in a JPEG and returns its raw TIFF
+/// payload (the bytes right after the `Exif\0\0` header), if any.
+fn find_existing_tiff(data: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+  let mut found = None;
+  rewrite_segments(data, |marker, segment| {
+    if found.is_none() && marker == APP1_MARKER && segment.get(4..10) == Some(EXIF_HEADER) {
+      found = Some(segment[10..].to_vec());
+    }
+    Some(segment.to_vec())
+  })?;
+  Ok(found)
+}
+
+/// Walks a JPEG's marker segments, handing each one to `transform` (which may
+/// drop it by returning `None`, or keep/replace it by returning `Some`), and
+/// returns the reassembled file. Entropy-coded scan data after SOS is copied
+/// through untouched.
+fn rewrite_segments(
+  data: &[u8],
+  mut transform: impl FnMut(u8, &[u8]) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+  if data.len() < 2 || data[0..2] != SOI {
+    return Err(Error::new(ErrorKind::InvalidData, "Not a JPEG file (missing SOI marker)"));
+  }
+
+  let mut out = Vec::with_capacity(data.len());
+  out.extend_from_slice(&SOI);
+
+  let mut i = 2;
+  while i + 1 < data.len() {
+    if data[i] != 0xFF {
+      out.extend_from_slice(&data[i..]);
+      break;
+    }
+
+    let marker = data[i + 1];
+
+    // SOS: everything after this is entropy-coded image data, copy verbatim.
+    if marker == 0xDA {
+      out.extend_from_slice(&data[i..]);
+      break;
+    }
+
+    // Fill bytes (0xFF padding before a marker) pass through one at a time.
+    if marker == 0xFF {
+      out.push(0xFF);
+      i += 1;
+      continue;
+    }
+
+    // Markers with no payload (TEM, restart markers) just pass through.
+    if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+      out.extend_from_slice(&data[i..i + 2]);
+      i += 2;
+      continue;
+    }
+
+    if i + 3 >= data.len() {
+      break;
+    }
+    let segment_len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+    let segment_end = (i + 2 + segment_len).min(data.len());
+
+    if let Some(replacement) = transform(marker, &data[i..segment_end]) {
+      out.extend_from_slice(&replacement);
+    }
+
+    i = segment_end;
+  }
+
+  Ok(out)
+}
+
+fn build_app1(tiff: &[u8]) -> Vec<u8> {
+  let mut segment = Vec::with_capacity(4 + EXIF_HEADER.len() + tiff.len());
+  segment.push(0xFF);
+  segment.push(APP1_MARKER);
+
+  let payload_len = 2 + EXIF_HEADER.len() + tiff.len(); // length field itself is included
+  segment.extend_from_slice(&(payload_len as u16).to_be_bytes());
+  segment.extend_from_slice(EXIF_HEADER);
+  segment.extend_from_slice(tiff);
+  segment
+}
+
+/// Builds a minimal little-endian TIFF structure with a single IFD0
+/// containing only the given ASCII fields, sorted ascending by tag as the
+/// TIFF spec requires (guaranteed here since `fields` is a `BTreeMap`).
+/// Only used when the image has no pre-existing Exif to patch in place, so
+/// there's nothing else for this structure to preserve.
+fn build_minimal_tiff(fields: &BTreeMap<u16, String>) -> Vec<u8> {
+  const IFD_OFFSET: u32 = 8;
+
+  let entry_count = fields.len() as u16;
+  let ifd_size = 2 + entry_count as usize * 12 + 4;
+  let mut overflow_offset = IFD_OFFSET + ifd_size as u32;
+
+  let mut ifd = Vec::new();
+  ifd.extend_from_slice(&entry_count.to_le_bytes());
+
+  let mut overflow = Vec::new();
+
+  for (&tag, value) in fields {
+    let mut bytes = value.clone().into_bytes();
+    bytes.push(0); // NUL-terminated, per the TIFF ASCII type
+    let count = bytes.len() as u32;
+
+    ifd.extend_from_slice(&tag.to_le_bytes());
+    ifd.extend_from_slice(&2u16.to_le_bytes()); // type 2 = ASCII
+    ifd.extend_from_slice(&count.to_le_bytes());
+
+    if bytes.len() <= 4 {
+      let mut value_field = [0u8; 4];
+      value_field[..bytes.len()].copy_from_slice(&bytes);
+      ifd.extend_from_slice(&value_field);
+    } else {
+      ifd.extend_from_slice(&overflow_offset.to_le_bytes());
+      overflow.extend_from_slice(&bytes);
+      overflow_offset += bytes.len() as u32;
+    }
+  }
+
+  ifd.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+
+  let mut tiff = Vec::new();
+  tiff.extend_from_slice(b"II");
+  tiff.extend_from_slice(&42u16.to_le_bytes());
+  tiff.extend_from_slice(&IFD_OFFSET.to_le_bytes());
+  tiff.extend_from_slice(&ifd);
+  tiff.extend_from_slice(&overflow);
+  tiff
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn decode_ascii_entry(tiff: &[u8], be: bool, entry_pos: usize) -> String {
+    let count = read_u32(tiff, entry_pos + 4, be) as usize;
+    let bytes = if count <= 4 {
+      tiff[entry_pos + 8..entry_pos + 8 + count].to_vec()
+    } else {
+      let offset = read_u32(tiff, entry_pos + 8, be) as usize;
+      tiff[offset..offset + count].to_vec()
+    };
+    String::from_utf8(bytes[..count - 1].to_vec()).unwrap() // drop the trailing NUL
+  }
+
+  #[test]
+  fn set_inserts_a_tag_with_no_existing_entry() {
+    let mut tiff = build_minimal_tiff(&BTreeMap::new());
+    apply_edits_to_tiff(&mut tiff, &[Edit::Set("Make".to_string(), "Canon".to_string())]).unwrap();
+
+    let entry_pos = find_entry(&tiff, false, 8, tag_id("Make").unwrap()).unwrap().unwrap();
+    assert_eq!(decode_ascii_entry(&tiff, false, entry_pos), "Canon");
+  }
+
+  #[test]
+  fn set_grows_an_inline_value_past_four_bytes() {
+    let mut fields = BTreeMap::new();
+    fields.insert(tag_id("Make").unwrap(), "A".to_string()); // 2 bytes with NUL: stored inline
+    let mut tiff = build_minimal_tiff(&fields);
+
+    apply_edits_to_tiff(&mut tiff, &[Edit::Set("Make".to_string(), "A Much Longer Manufacturer Name".to_string())]).unwrap();
+
+    let entry_pos = find_entry(&tiff, false, 8, tag_id("Make").unwrap()).unwrap().unwrap();
+    assert_eq!(decode_ascii_entry(&tiff, false, entry_pos), "A Much Longer Manufacturer Name");
+  }
+
+  #[test]
+  fn delete_present_tag_zeroes_it_and_absent_tag_is_a_no_op() {
+    let mut fields = BTreeMap::new();
+    fields.insert(tag_id("Make").unwrap(), "Canon".to_string());
+    let mut tiff = build_minimal_tiff(&fields);
+
+    apply_edits_to_tiff(&mut tiff, &[Edit::Delete("Make".to_string())]).unwrap();
+    assert!(find_entry(&tiff, false, 8, tag_id("Make").unwrap()).unwrap().is_none());
+
+    // Deleting a tag that was never present must not panic or error.
+    apply_edits_to_tiff(&mut tiff, &[Edit::Delete("Model".to_string())]).unwrap();
+  }
+
+  #[test]
+  fn malformed_ifd0_offset_is_rejected_instead_of_panicking() {
+    // "II" + version(2 bytes) + a bogus, far-out-of-range little-endian offset.
+    let mut tiff = b"II".to_vec();
+    tiff.extend_from_slice(&42u16.to_le_bytes());
+    tiff.extend_from_slice(&0x00FF_FFFFu32.to_le_bytes());
+
+    let err = apply_edits_to_tiff(&mut tiff, &[Edit::Set("Make".to_string(), "Canon".to_string())]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+  }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+/// What we need out of a HEIC/HEIF file: the primary image item's declared
+/// dimensions, and its Exif metadata item's raw TIFF bytes (if present).
+pub struct HeifInfo {
+  pub width: u32,
+  pub height: u32,
+  pub exif: Option<Vec<u8>>,
+}
+
+struct BoxHeader {
+  box_type: [u8; 4],
+  header_len: usize,
+  content_len: usize,
+}
+
+/// Parses the box hierarchy of an ISO/IEC 14496-12 (ISO Base Media File
+/// Format) file: `ftyp` identifies the brand, and `meta` -> `iinf`/`iloc`/
+/// `iprp` locates the primary image item and its Exif metadata item. This is
+/// the same structure HEIC/HEIF files use.
+pub fn parse(data: &[u8]) -> Result<HeifInfo, Error> {
+  if find_box(data, b"ftyp").is_none() {
+    return Err(Error::new(ErrorKind::InvalidData, "Not an ISO-BMFF (HEIF) file: missing ftyp box"));
+  }
+
+  let (meta_payload, _) = find_box(data, b"meta")
+    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No meta box found"))?;
+
+  if meta_payload.len() < 4 {
+    return Err(Error::new(ErrorKind::InvalidData, "Truncated meta box"));
+  }
+  // meta is a FullBox: 1 byte version + 3 bytes flags precede its children.
+  let meta_children = &meta_payload[4..];
+
+  let primary_item_id = find_primary_item_id(meta_children)
+    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No pitm box found"))?;
+
+  let (width, height) = parse_ispe_for_item(meta_children, primary_item_id).unwrap_or((0, 0));
+
+  let item_types = parse_iinf(meta_children);
+  let item_locations = parse_iloc(meta_children);
+
+  let exif = item_types.iter()
+    .find(|(_, item_type)| item_type == "Exif")
+    .and_then(|(id, _)| item_locations.get(id))
+    .and_then(|&(offset, length)| extract_exif_tiff(data, offset, length));
+
+  Ok(HeifInfo { width, height, exif })
+}
+
+/// Reads the box header at `data[pos..]`: a 4-byte size + 4-byte type, with
+/// size == 1 meaning a following 8-byte 64-bit size, and size == 0 meaning
+/// "to end of file".
+fn read_box_header(data: &[u8], pos: usize) -> Option<BoxHeader> {
+  if pos + 8 > data.len() {
+    return None;
+  }
+  let size32 = u32::from_be_bytes(data[pos..pos + 4].try_into().ok()?);
+  let box_type: [u8; 4] = data[pos + 4..pos + 8].try_into().ok()?;
+
+  let (header_len, total_len) = if size32 == 1 {
+    if pos + 16 > data.len() {
+      return None;
+    }
+    let size64 = u64::from_be_bytes(data[pos + 8..pos + 16].try_into().ok()?);
+    (16, size64 as usize)
+  } else if size32 == 0 {
+    (8, data.len() - pos)
+  } else {
+    (8, size32 as usize)
+  };
+
+  if total_len < header_len || pos + total_len > data.len() {
+    return None;
+  }
+
+  Some(BoxHeader { box_type, header_len, content_len: total_len - header_len })
+}
+
+/// Finds the first direct child box of `target_type` inside `data`, recursing
+/// is left to the caller since different boxes need different child lists.
+fn find_box<'a>(data: &'a [u8], target_type: &[u8; 4]) -> Option<(&'a [u8], usize)> {
+  let mut pos = 0;
+  while pos < data.len() {
+    let header = read_box_header(data, pos)?;
+    let content_start = pos + header.header_len;
+    let content_end = content_start + header.content_len;
+    if &header.box_type == target_type {
+      return Some((&data[content_start..content_end], content_start));
+    }
+    pos = content_end;
+  }
+  None
+}
+
+fn list_child_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+  let mut boxes = Vec::new();
+  let mut pos = 0;
+  while let Some(header) = read_box_header(data, pos) {
+    let content_start = pos + header.header_len;
+    let content_end = content_start + header.content_len;
+    boxes.push((header.box_type, &data[content_start..content_end]));
+    pos = content_end;
+  }
+  boxes
+}
+
+fn find_primary_item_id(meta_children: &[u8]) -> Option<u32> {
+  let (payload, _) = find_box(meta_children, b"pitm")?;
+  if payload.len() < 4 {
+    return None;
+  }
+  let version = payload[0];
+  let body = &payload[4..];
+
+  if version == 0 {
+    Some(u16::from_be_bytes(body.get(0..2)?.try_into().ok()?) as u32)
+  } else {
+    Some(u32::from_be_bytes(body.get(0..4)?.try_into().ok()?))
+  }
+}
+
+/// Walks the `iinf` (item info) box and returns each item's (id, four-char
+/// type code), e.g. `(2, "Exif")` or `(1, "hvc1")`.
+fn parse_iinf(meta_children: &[u8]) -> Vec<(u32, String)> {
+  let mut result = Vec::new();
+  let Some((payload, _)) = find_box(meta_children, b"iinf") else { return result };
+  if payload.len() < 4 {
+    return result;
+  }
+
+  let version = payload[0];
+  let mut pos = 4;
+  let entry_count = if version == 0 {
+    let Some(bytes) = payload.get(pos..pos + 2) else { return result };
+    pos += 2;
+    u16::from_be_bytes(bytes.try_into().unwrap()) as u32
+  } else {
+    let Some(bytes) = payload.get(pos..pos + 4) else { return result };
+    pos += 4;
+    u32::from_be_bytes(bytes.try_into().unwrap())
+  };
+
+  for _ in 0..entry_count {
+    let Some(header) = read_box_header(payload, pos) else { break };
+    let content_start = pos + header.header_len;
+    let content_end = content_start + header.content_len;
+    if &header.box_type == b"infe" {
+      if let Some(entry) = parse_infe(&payload[content_start..content_end]) {
+        result.push(entry);
+      }
+    }
+    pos = content_end;
+  }
+
+  result
+}
+
+fn parse_infe(payload: &[u8]) -> Option<(u32, String)> {
+  if payload.len() < 4 {
+    return None;
+  }
+  let version = payload[0];
+  let body = &payload[4..];
+
+  let (item_id, type_offset) = match version {
+    2 => (u16::from_be_bytes(body.get(0..2)?.try_into().ok()?) as u32, 4),
+    3 => (u32::from_be_bytes(body.get(0..4)?.try_into().ok()?), 6),
+    _ => return None, // older infe layouts aren't produced by modern HEIF encoders
+  };
+
+  let item_type = std::str::from_utf8(body.get(type_offset..type_offset + 4)?).ok()?.to_string();
+  Some((item_id, item_type))
+}
+
+/// Walks the `iloc` (item location) box and returns each item's (offset,
+/// length) in the file, taking only the first extent of each item (camera
+/// HEIC files store each item as a single contiguous extent).
+fn parse_iloc(meta_children: &[u8]) -> HashMap<u32, (u64, u64)> {
+  parse_iloc_inner(meta_children).unwrap_or_default()
+}
+
+fn parse_iloc_inner(meta_children: &[u8]) -> Option<HashMap<u32, (u64, u64)>> {
+  let (payload, _) = find_box(meta_children, b"iloc")?;
+  if payload.len() < 4 {
+    return None;
+  }
+
+  let version = payload[0];
+  let mut pos = 4;
+
+  let sizes_byte1 = *payload.get(pos)?;
+  let sizes_byte2 = *payload.get(pos + 1)?;
+  let offset_size = (sizes_byte1 >> 4) as usize;
+  let length_size = (sizes_byte1 & 0x0F) as usize;
+  let base_offset_size = (sizes_byte2 >> 4) as usize;
+  let index_size = (sizes_byte2 & 0x0F) as usize;
+  pos += 2;
+
+  let item_count = if version < 2 {
+    let v = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?) as u32;
+    pos += 2;
+    v
+  } else {
+    let v = u32::from_be_bytes(payload.get(pos..pos + 4)?.try_into().ok()?);
+    pos += 4;
+    v
+  };
+
+  let mut result = HashMap::new();
+
+  for _ in 0..item_count {
+    let item_id = if version < 2 {
+      let v = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?) as u32;
+      pos += 2;
+      v
+    } else {
+      let v = u32::from_be_bytes(payload.get(pos..pos + 4)?.try_into().ok()?);
+      pos += 4;
+      v
+    };
+
+    if version == 1 || version == 2 {
+      pos += 2; // construction_method (+ reserved)
+    }
+    pos += 2; // data_reference_index
+
+    let base_offset = read_uint(payload, &mut pos, base_offset_size)?;
+    let extent_count = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?);
+    pos += 2;
+
+    let mut first_extent = None;
+    for _ in 0..extent_count {
+      if (version == 1 || version == 2) && index_size > 0 {
+        pos += index_size; // extent_index, unused: we only need byte ranges
+      }
+      let extent_offset = read_uint(payload, &mut pos, offset_size)?;
+      let extent_length = read_uint(payload, &mut pos, length_size)?;
+      if first_extent.is_none() {
+        first_extent = Some((base_offset + extent_offset, extent_length));
+      }
+    }
+
+    if let Some(extent) = first_extent {
+      result.insert(item_id, extent);
+    }
+  }
+
+  Some(result)
+}
+
+fn read_uint(data: &[u8], pos: &mut usize, size: usize) -> Option<u64> {
+  if size == 0 {
+    return Some(0);
+  }
+  let bytes = data.get(*pos..*pos + size)?;
+  *pos += size;
+  Some(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+}
+
+/// Resolves the primary item's `ispe` (image spatial extents) property by
+/// walking `iprp` -> `ipco` (the property list) and `ipma` (the item-to-
+/// property index map).
+fn parse_ispe_for_item(meta_children: &[u8], item_id: u32) -> Option<(u32, u32)> {
+  let (iprp_payload, _) = find_box(meta_children, b"iprp")?;
+  let (ipco_payload, _) = find_box(iprp_payload, b"ipco")?;
+  let properties = list_child_boxes(ipco_payload);
+
+  let (ipma_payload, _) = find_box(iprp_payload, b"ipma")?;
+  let associations = parse_ipma(ipma_payload)?;
+
+  let indices = associations.get(&item_id)?;
+  for &index in indices {
+    if index == 0 || index as usize > properties.len() {
+      continue;
+    }
+    let (box_type, payload) = &properties[index as usize - 1];
+    if box_type == b"ispe" && payload.len() >= 12 {
+      // ispe: FullBox (4 bytes) + width (u32) + height (u32)
+      let width = u32::from_be_bytes(payload[4..8].try_into().ok()?);
+      let height = u32::from_be_bytes(payload[8..12].try_into().ok()?);
+      return Some((width, height));
+    }
+  }
+
+  None
+}
+
+fn parse_ipma(payload: &[u8]) -> Option<HashMap<u32, Vec<u32>>> {
+  if payload.len() < 8 {
+    return None;
+  }
+  let version = payload[0];
+  let flags = u32::from_be_bytes([0, payload[1], payload[2], payload[3]]);
+  let large_ids = flags & 1 != 0;
+  let mut pos = 4;
+
+  let entry_count = u32::from_be_bytes(payload.get(pos..pos + 4)?.try_into().ok()?);
+  pos += 4;
+
+  let mut result = HashMap::new();
+
+  for _ in 0..entry_count {
+    let item_id = if version == 0 {
+      let v = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?) as u32;
+      pos += 2;
+      v
+    } else {
+      let v = u32::from_be_bytes(payload.get(pos..pos + 4)?.try_into().ok()?);
+      pos += 4;
+      v
+    };
+
+    let association_count = *payload.get(pos)?;
+    pos += 1;
+
+    let mut indices = Vec::new();
+    for _ in 0..association_count {
+      let index = if large_ids {
+        let raw = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?);
+        pos += 2;
+        (raw & 0x7FFF) as u32
+      } else {
+        let raw = *payload.get(pos)?;
+        pos += 1;
+        (raw & 0x7F) as u32
+      };
+      indices.push(index);
+    }
+
+    result.insert(item_id, indices);
+  }
+
+  Some(result)
+}
+
+/// An `Exif` HEIF item's payload begins with a 4-byte big-endian offset to
+/// the start of the TIFF header (almost always 6, to skip an "Exif\0\0"
+/// marker); what follows is a standalone TIFF blob, which the `exif` crate
+/// can read directly.
+fn extract_exif_tiff(file_data: &[u8], offset: u64, length: u64) -> Option<Vec<u8>> {
+  let start = usize::try_from(offset).ok()?;
+  let end = start.checked_add(usize::try_from(length).ok()?)?;
+  let item_data = file_data.get(start..end)?;
+
+  if item_data.len() < 4 {
+    return None;
+  }
+  let tiff_header_offset = u32::from_be_bytes(item_data[0..4].try_into().ok()?) as usize;
+  let tiff_start = 4usize.checked_add(tiff_header_offset)?;
+  item_data.get(tiff_start..).map(|s| s.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_box(box_type: &[u8; 4], payload: Vec<u8>) -> Vec<u8> {
+    let mut b = Vec::with_capacity(8 + payload.len());
+    b.extend(((8 + payload.len()) as u32).to_be_bytes());
+    b.extend_from_slice(box_type);
+    b.extend(payload);
+    b
+  }
+
+  /// Hand-builds a minimal but structurally complete HEIC file: `ftyp` +
+  /// `meta` with a primary item (id 1, with an `ispe` size property) and a
+  /// separate Exif item (id 2) whose `iloc` extent points at a raw blob
+  /// appended after `meta`.
+  fn build_synthetic_heic(exif_tiff_bytes: &[u8]) -> Vec<u8> {
+    let ftyp = make_box(b"ftyp", {
+      let mut p = b"heic".to_vec();
+      p.extend(0u32.to_be_bytes());
+      p.extend_from_slice(b"heic");
+      p
+    });
+
+    let pitm = make_box(b"pitm", {
+      let mut p = vec![0u8, 0, 0, 0]; // version 0, flags 0
+      p.extend(1u16.to_be_bytes()); // primary item id = 1
+      p
+    });
+
+    let infe = make_box(b"infe", {
+      let mut p = vec![2u8, 0, 0, 0]; // version 2, flags 0
+      p.extend(2u16.to_be_bytes()); // item id = 2 (Exif item)
+      p.extend(0u16.to_be_bytes()); // item_protection_index
+      p.extend_from_slice(b"Exif");
+      p
+    });
+    let iinf = make_box(b"iinf", {
+      let mut p = vec![0u8, 0, 0, 0]; // version 0, flags 0
+      p.extend(1u16.to_be_bytes()); // entry_count
+      p.extend(infe);
+      p
+    });
+
+    let exif_item_payload = {
+      let mut p = 6u32.to_be_bytes().to_vec(); // TIFF starts 6 bytes in, skipping "Exif\0\0"
+      p.extend_from_slice(b"Exif\0\0");
+      p.extend_from_slice(exif_tiff_bytes);
+      p
+    };
+
+    let mut iloc_payload = vec![0u8, 0, 0, 0]; // version 0, flags 0
+    iloc_payload.push(0x44); // offset_size = 4, length_size = 4
+    iloc_payload.push(0x00); // base_offset_size = 0, index_size = 0
+    iloc_payload.extend(1u16.to_be_bytes()); // item_count = 1
+    iloc_payload.extend(2u16.to_be_bytes()); // item_id = 2
+    iloc_payload.extend(0u16.to_be_bytes()); // data_reference_index
+    iloc_payload.extend(1u16.to_be_bytes()); // extent_count = 1
+    let extent_offset_pos_in_iloc_payload = iloc_payload.len();
+    iloc_payload.extend(0u32.to_be_bytes()); // extent_offset placeholder, patched in below
+    iloc_payload.extend((exif_item_payload.len() as u32).to_be_bytes()); // extent_length
+    let iloc = make_box(b"iloc", iloc_payload);
+
+    let ispe = make_box(b"ispe", {
+      let mut p = vec![0u8, 0, 0, 0];
+      p.extend(800u32.to_be_bytes());
+      p.extend(600u32.to_be_bytes());
+      p
+    });
+    let ipco = make_box(b"ipco", ispe);
+    let ipma = make_box(b"ipma", {
+      let mut p = vec![0u8, 0, 0, 0]; // version 0, flags 0 (short item ids)
+      p.extend(1u32.to_be_bytes()); // entry_count = 1
+      p.extend(1u16.to_be_bytes()); // item_id = 1 (primary item)
+      p.push(1); // association_count
+      p.push(1); // property index 1 (1-based -> ipco[0] = ispe)
+      p
+    });
+    let iprp = make_box(b"iprp", { let mut p = ipco; p.extend(ipma); p });
+
+    let meta_children_before_iloc = { let mut p = pitm; p.extend(iinf); p };
+    let iloc_pos_in_meta_children = meta_children_before_iloc.len();
+    let meta_children = { let mut p = meta_children_before_iloc; p.extend(iloc); p.extend(iprp); p };
+
+    let meta_payload = { let mut p = vec![0u8, 0, 0, 0]; p.extend(meta_children); p }; // version 0, flags 0
+    let meta = make_box(b"meta", meta_payload);
+
+    let extent_offset_pos_in_file =
+      ftyp.len() + 8 /* meta box header */ + 4 /* meta FullBox header */
+      + iloc_pos_in_meta_children + 8 /* iloc box header */ + extent_offset_pos_in_iloc_payload;
+
+    let mut data = ftyp;
+    data.extend(meta);
+    let exif_item_offset = data.len() as u32;
+    data[extent_offset_pos_in_file..extent_offset_pos_in_file + 4]
+      .copy_from_slice(&exif_item_offset.to_be_bytes());
+    data.extend(exif_item_payload);
+    data
+  }
+
+  #[test]
+  fn parses_a_minimal_synthetic_heic_end_to_end() {
+    let exif_tiff_bytes = b"FAKETIFFDATA".to_vec();
+    let data = build_synthetic_heic(&exif_tiff_bytes);
+
+    let info = parse(&data).unwrap();
+    assert_eq!(info.width, 800);
+    assert_eq!(info.height, 600);
+    assert_eq!(info.exif.unwrap(), exif_tiff_bytes);
+  }
+
+  #[test]
+  fn rejects_data_with_no_ftyp_box() {
+    assert!(parse(b"not an ISO-BMFF file at all").is_err());
+  }
+
+  #[test]
+  fn rejects_a_meta_box_whose_declared_size_exceeds_the_buffer() {
+    let ftyp = make_box(b"ftyp", {
+      let mut p = b"heic".to_vec();
+      p.extend(0u32.to_be_bytes());
+      p.extend_from_slice(b"heic");
+      p
+    });
+
+    let mut data = ftyp;
+    data.extend(1000u32.to_be_bytes()); // meta box claims 1000 bytes...
+    data.extend_from_slice(b"meta");
+    data.extend([0u8, 0, 0, 0]); // ...but only 4 bytes of content actually follow
+
+    assert!(parse(&data).is_err());
+  }
+}
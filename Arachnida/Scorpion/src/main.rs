@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use font8x8::{BASIC_FONTS, UnicodeFonts};
 use minifb::{Key, Window, WindowOptions};
 use clap::Parser;
@@ -7,6 +7,9 @@ use image::GenericImageView;
 mod metadata;
 use metadata::Metadata;
 
+mod exif_edit;
+mod heif;
+
 const WIDTH: usize = 840;
 const HEIGHT: usize = 460;
 
@@ -17,6 +20,18 @@ const IMAGE_DISPLAY_WIDTH: usize = 600;
 struct Args {
   /// Paths of images to handle
   path: Vec<String>,
+
+  /// Strip all EXIF/IPTC/XMP metadata (including GPS) and write a clean copy
+  #[arg(long)]
+  strip: bool,
+
+  /// Set a metadata tag, formatted as TAG=VALUE (repeatable)
+  #[arg(long = "set", value_name = "TAG=VALUE")]
+  set: Vec<String>,
+
+  /// Delete a metadata tag by name (repeatable)
+  #[arg(long = "delete", value_name = "TAG")]
+  delete: Vec<String>,
 }
 
 struct DisplayState {
@@ -35,29 +50,41 @@ struct ImageData {
 
 impl ImageData {
   fn from_path(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-    let img = image::open(path)?;
-    let dimensions = img.dimensions();
     let path_buf = PathBuf::from(path);
     let metadata = Metadata::from_file(&path_buf)?;
 
-    // Minifb (gui lib) only takes u32 RGBA as argument to display 
-    let rgb_img = img.to_rgba8();
-    let buffer: Vec<u32> = rgb_img.pixels()
-      .map(|p| {
-        let r: u32 = p[0] as u32;
-        let g: u32 = p[1] as u32;
-        let b: u32 = p[2] as u32;
-        let a: u32 = p[3] as u32;
-        (a << 24) | (r << 16) | (g << 8) | b
-      })
-      .collect();
-
-      Ok(ImageData {
-        buffer,
-        width: dimensions.0 as usize,
-        height: dimensions.1 as usize,
-        metadata,
-      })
+    let is_heif = path_buf.extension()
+      .and_then(|ext| ext.to_str())
+      .map(|ext| matches!(ext.to_lowercase().as_str(), "heic" | "heif"))
+      .unwrap_or(false);
+
+    let (buffer, width, height) = if is_heif {
+      // `image` can't decode HEIF pixel data; show a placeholder the right
+      // size so the metadata panel (which the box parser does populate) is
+      // still browsable.
+      let width = metadata.width as usize;
+      let height = metadata.height as usize;
+      (vec![0xFF404040; width * height], width, height)
+    } else {
+      let img = image::open(path)?;
+      let dimensions = img.dimensions();
+
+      // Minifb (gui lib) only takes u32 RGBA as argument to display
+      let rgb_img = img.to_rgba8();
+      let buffer: Vec<u32> = rgb_img.pixels()
+        .map(|p| {
+          let r: u32 = p[0] as u32;
+          let g: u32 = p[1] as u32;
+          let b: u32 = p[2] as u32;
+          let a: u32 = p[3] as u32;
+          (a << 24) | (r << 16) | (g << 8) | b
+        })
+        .collect();
+
+      (buffer, dimensions.0 as usize, dimensions.1 as usize)
+    };
+
+    Ok(ImageData { buffer, width, height, metadata })
   }
 
   // Scale the image to the reserved space
@@ -85,8 +112,13 @@ fn main() {
 
   let args = Args::parse();
 
+  if args.strip || !args.set.is_empty() || !args.delete.is_empty() {
+    run_edit_mode(&args);
+    return;
+  }
+
   if check_args(&args) == false {
-    eprintln!("Error parsing arguments, supported extensions are : jpg / jpeg / png / gif / bmp");
+    eprintln!("Error parsing arguments, supported extensions are : jpg / jpeg / png / gif / bmp / heic / heif");
     return;
   }
 
@@ -96,9 +128,45 @@ fn main() {
   }
 }
 
+fn run_edit_mode(args: &Args) {
+  for path in &args.path {
+    if let Err(e) = edit_one(path, args) {
+      eprintln!("Error editing {}: {}", path, e);
+    }
+  }
+}
+
+fn edit_one(path: &str, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+  let path_buf = PathBuf::from(path);
+
+  let (bytes, suffix) = if args.strip {
+    (exif_edit::strip_metadata(&path_buf)?, "stripped")
+  } else {
+    let mut edits = Vec::new();
+    for set_arg in &args.set {
+      edits.push(exif_edit::parse_set(set_arg)?);
+    }
+    for tag in &args.delete {
+      edits.push(exif_edit::parse_delete(tag));
+    }
+    (exif_edit::apply_edits(&path_buf, &edits)?, "edited")
+  };
+
+  let output_path = with_suffix(&path_buf, suffix);
+  std::fs::write(&output_path, bytes)?;
+  println!("Wrote {} copy: {}", suffix, output_path.display());
+  Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+  let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("jpg");
+  path.with_file_name(format!("{}_{}.{}", stem, suffix, extension))
+}
+
 fn check_args(args: &Args) -> bool {
 
-  let valid_extensions = ["jpg", "jpeg", "png", "gif", "bmp"];
+  let valid_extensions = ["jpg", "jpeg", "png", "gif", "bmp", "heic", "heif"];
 
   for path in &args.path {
     if let Some(extension) = path.split('.').last() {
@@ -168,7 +236,16 @@ fn display_loop(mut state: DisplayState) {
         format!("Created: {}", metadata.created.map_or("Unknown".to_string(), |dt| dt.to_string())),
         format!("Dimensions: {}x{}", metadata.width, metadata.height),
         format!("Color Type: {}", metadata.color_type),
+        format!("Make: {}", metadata.camera_make.as_deref().unwrap_or("Unknown")),
         format!("Camera Model: {}", metadata.camera_model.as_deref().unwrap_or("Unknown")),
+        format!("Lens: {}", metadata.lens_model.as_deref().unwrap_or("Unknown")),
+        format!("Software: {}", metadata.software.as_deref().unwrap_or("Unknown")),
+        format!("Orientation: {}", metadata.orientation.as_deref().unwrap_or("Unknown")),
+        format!("Date Taken: {}", metadata.date_time_original.as_deref().unwrap_or("Unknown")),
+        format!("GPS: {}", match (metadata.gps_latitude, metadata.gps_longitude) {
+          (Some(lat), Some(lon)) => format!("{:.6}, {:.6}", lat, lon),
+          _ => "Unknown".to_string(),
+        }),
       ];
 
       for (i, text) in metadata_texts.iter().enumerate() {
@@ -1,7 +1,10 @@
+use std::io::Cursor;
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
 use image::GenericImageView;
 
+use crate::heif;
+
 #[derive(Debug)]
 pub struct Metadata {
 
@@ -12,7 +15,14 @@ pub struct Metadata {
   pub height: u32,
   pub color_type: String,
 
-  pub camera_model: Option<String>
+  pub camera_model: Option<String>,
+  pub camera_make: Option<String>,
+  pub software: Option<String>,
+  pub lens_model: Option<String>,
+  pub date_time_original: Option<String>,
+  pub orientation: Option<String>,
+  pub gps_latitude: Option<f64>,
+  pub gps_longitude: Option<f64>,
 }
 
 impl Metadata {
@@ -21,29 +31,88 @@ impl Metadata {
 
     let fs_metadata = std::fs::metadata(path)?;
 
-    let img = image::open(path)?;
-    let (width, height) = img.dimensions();
+    let is_heif = path.extension()
+      .and_then(|ext| ext.to_str())
+      .map(|ext| matches!(ext.to_lowercase().as_str(), "heic" | "heif"))
+      .unwrap_or(false);
+
+    // HEIC/HEIF isn't decodable by the `image` crate, so its dimensions and
+    // Exif come from our own ISO-BMFF box walker instead.
+    let (width, height, color_type, heif_exif) = if is_heif {
+      let file_data = std::fs::read(path)?;
+      let info = heif::parse(&file_data)?;
+      (info.width, info.height, "HEIF".to_string(), info.exif)
+    } else {
+      let img = image::open(path)?;
+      let (width, height) = img.dimensions();
+      (width, height, format!("{:?}", img.color()), None)
+    };
 
     let mut metadata = Metadata {
       size_bytes: fs_metadata.len(),
       created: fs_metadata.created().ok().map(DateTime::from),
       width,
       height,
-      color_type: format!("{:?}", img.color()),
+      color_type,
       camera_model: None,
+      camera_make: None,
+      software: None,
+      lens_model: None,
+      date_time_original: None,
+      orientation: None,
+      gps_latitude: None,
+      gps_longitude: None,
+    };
+
+    let exif_reader = exif::Reader::new();
+    let exif_result = if let Some(tiff_bytes) = heif_exif {
+      exif_reader.read_from_container(&mut Cursor::new(tiff_bytes)).ok()
+    } else {
+      std::fs::File::open(path).ok()
+        .and_then(|file| exif_reader.read_from_container(&mut std::io::BufReader::new(file)).ok())
     };
 
-    //Try to get EXIF data (most likely for jpg/jpeg)
-    if let Ok(file) = std::fs::File::open(path) {
-      if let Ok(exif) = exif::Reader::new()
-        .read_from_container(&mut std::io::BufReader::new(file)) 
-      {
-        if let Some(model) = exif.get_field(exif::Tag::Model, exif::In::PRIMARY) {
-          metadata.camera_model = Some(model.display_value().to_string());
-        }
-      }
+    if let Some(exif) = exif_result {
+      metadata.camera_model = display_value(&exif, exif::Tag::Model);
+      metadata.camera_make = display_value(&exif, exif::Tag::Make);
+      metadata.software = display_value(&exif, exif::Tag::Software);
+      metadata.lens_model = display_value(&exif, exif::Tag::LensModel);
+      metadata.date_time_original = display_value(&exif, exif::Tag::DateTimeOriginal);
+      metadata.orientation = display_value(&exif, exif::Tag::Orientation);
+      metadata.gps_latitude = gps_decimal(&exif, exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, "S");
+      metadata.gps_longitude = gps_decimal(&exif, exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, "W");
     }
 
     Ok(metadata)
   }
-}
\ No newline at end of file
+}
+
+fn display_value(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+  exif.get_field(tag, exif::In::PRIMARY)
+    .map(|field| field.display_value().to_string())
+}
+
+/// Converts a GPS coordinate stored as (degrees, minutes, seconds) rationals
+/// into signed decimal degrees, negating it if the matching ref tag
+/// (e.g. `GPSLatitudeRef` = "S" or `GPSLongitudeRef` = "W") says so.
+fn gps_decimal(exif: &exif::Exif, value_tag: exif::Tag, ref_tag: exif::Tag, negative_ref: &str) -> Option<f64> {
+  let field = exif.get_field(value_tag, exif::In::PRIMARY)?;
+  let exif::Value::Rational(ref rationals) = field.value else { return None };
+  if rationals.len() < 3 {
+    return None;
+  }
+
+  let degrees = rationals[0].to_f64();
+  let minutes = rationals[1].to_f64();
+  let seconds = rationals[2].to_f64();
+  let mut decimal = degrees + minutes / 60.0 + seconds / 3600.0;
+
+  if let Some(reference) = display_value(exif, ref_tag) {
+    // display_value() renders ASCII values in double quotes, e.g. "S".
+    if reference.trim().trim_matches('"') == negative_ref {
+      decimal = -decimal;
+    }
+  }
+
+  Some(decimal)
+}
@@ -1,14 +1,22 @@
 use clap::Parser;
 use scraper::{Html, Selector};
+use sha2::{Digest, Sha256};
 use ureq::Agent;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
 use std::vec::Vec;
 use url::Url;
 use std::fs;
 use std::path::Path;
 
+mod safe_path;
+
 type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+const DEFAULT_JOBS: usize = 4;
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
@@ -26,6 +34,19 @@ struct Args {
   /// Optional save path, default = ./data/
   #[arg(short = 'p', long, value_name = "PATH")]
   p: Option<String>,
+
+  /// Number of concurrent page-fetch and download workers, default = 4
+  #[arg(short = 'j', long, value_name = "NUMBER")]
+  jobs: Option<usize>,
+}
+
+/// The shared frontier of `(url, depth)` pairs still to crawl, plus a count
+/// of workers currently processing an item. Termination is detected when
+/// the queue is empty and no worker is active: at that point no one is left
+/// to add more work, so every worker can safely exit.
+struct Frontier {
+  queue: VecDeque<(String, u32)>,
+  active: usize,
 }
 
 #[derive(Clone)]
@@ -33,6 +54,7 @@ struct Settings {
   recursive: bool,
   depth: u32,
   path: String,
+  jobs: usize,
 }
 
 fn main() {
@@ -43,58 +65,169 @@ fn main() {
     recursive: args.r,
     depth: args.l.unwrap_or(5),
     path: args.p.unwrap_or("./data".to_string()),
+    jobs: args.jobs.unwrap_or(DEFAULT_JOBS).max(1),
   };
 
   let client: Agent = Agent::new();
+  let already_visited = Arc::new(Mutex::new(HashSet::<String>::new()));
+  let seen_hashes = Arc::new(Mutex::new(HashSet::<String>::new()));
+
+  let (image_tx, image_rx) = mpsc::channel::<String>();
+  let image_rx = Arc::new(Mutex::new(image_rx));
 
-  let already_visited = HashSet::<String>::new();
+  let download_workers: Vec<_> = (0..settings.jobs)
+    .map(|_| spawn_download_worker(client.clone(), settings.path.clone(), Arc::clone(&image_rx), Arc::clone(&seen_hashes)))
+    .collect();
 
-  if let Err(e) = get_page_images(&client, args.url, settings, 0, already_visited) {
-    eprintln!("Error getting images from page: {}", e);
+  already_visited.lock().unwrap().insert(args.url.clone());
+  let frontier = Arc::new((Mutex::new(Frontier { queue: VecDeque::from([(args.url, 0)]), active: 0 }), Condvar::new()));
+
+  let page_workers: Vec<_> = (0..settings.jobs)
+    .map(|_| spawn_page_worker(client.clone(), settings.clone(), Arc::clone(&already_visited), image_tx.clone(), Arc::clone(&frontier)))
+    .collect();
+
+  // Dropping our sender lets the download workers' `recv` calls fail once
+  // every page worker (each holding its own clone) has exited in turn.
+  drop(image_tx);
+
+  for worker in page_workers {
+    let _ = worker.join();
   }
+  for worker in download_workers {
+    let _ = worker.join();
+  }
+}
 
+/// Spawns a worker thread that pulls image URLs off `image_rx` and downloads
+/// them until the channel is closed, deduping by content hash against
+/// `seen_hashes` so the same image referenced from many pages is only saved
+/// once.
+fn spawn_download_worker(
+  client: Agent,
+  path: String,
+  image_rx: Arc<Mutex<mpsc::Receiver<String>>>,
+  seen_hashes: Arc<Mutex<HashSet<String>>>,
+) -> thread::JoinHandle<()> {
+  thread::spawn(move || loop {
+    let img_url = {
+      let rx = image_rx.lock().unwrap();
+      rx.recv()
+    };
+
+    match img_url {
+      Ok(url) => {
+        if let Err(e) = download_image(&client, &url, &path, &seen_hashes) {
+          eprintln!("Failed to download {}: {}", url, e);
+        }
+      }
+      Err(_) => break,
+    }
+  })
 }
 
-fn get_page_images(
-  client: &ureq::Agent,
-  url: String,
+/// Spawns a worker thread that pulls `(url, depth)` pairs off the shared
+/// `frontier` and processes them until the frontier is drained and no
+/// worker (including this one) is still active, at which point further
+/// work can never appear and the worker exits.
+fn spawn_page_worker(
+  client: Agent,
   settings: Settings,
-  current_depth: u32,
-  mut already_visited: HashSet<String>
-) -> Result<(), BoxedError>{
+  already_visited: Arc<Mutex<HashSet<String>>>,
+  image_tx: mpsc::Sender<String>,
+  frontier: Arc<(Mutex<Frontier>, Condvar)>,
+) -> thread::JoinHandle<()> {
+  thread::spawn(move || loop {
+    let item = match take_frontier_item(&frontier) {
+      Some(item) => item,
+      None => break,
+    };
+
+    let (url, depth) = item;
+    process_page(&client, url, depth, &settings, &already_visited, &image_tx, &frontier);
+
+    finish_frontier_item(&frontier);
+  })
+}
 
-  if !settings.recursive && current_depth >= 1 ||
-    settings.recursive && current_depth > settings.depth {
-    return Ok(()) 
+/// Blocks until either an item is available to take (marking this worker
+/// active) or the frontier is empty with no worker active, in which case
+/// there's nothing left to do and `None` is returned.
+fn take_frontier_item(frontier: &(Mutex<Frontier>, Condvar)) -> Option<(String, u32)> {
+  let (lock, condvar) = frontier;
+  let mut state = lock.lock().unwrap();
+  loop {
+    if let Some(item) = state.queue.pop_front() {
+      state.active += 1;
+      return Some(item);
+    }
+    if state.active == 0 {
+      // Wake any other worker still waiting here so it also observes
+      // "queue empty, nobody active" and exits.
+      condvar.notify_all();
+      return None;
+    }
+    state = condvar.wait(state).unwrap();
   }
+}
 
-  if already_visited.contains(&url) {
-    return Ok(())
+fn finish_frontier_item(frontier: &(Mutex<Frontier>, Condvar)) {
+  let (lock, condvar) = frontier;
+  let mut state = lock.lock().unwrap();
+  state.active -= 1;
+  if state.queue.is_empty() && state.active == 0 {
+    condvar.notify_all();
   }
-  already_visited.insert(url.clone());
+}
 
+fn process_page(
+  client: &ureq::Agent,
+  url: String,
+  current_depth: u32,
+  settings: &Settings,
+  already_visited: &Arc<Mutex<HashSet<String>>>,
+  image_tx: &mpsc::Sender<String>,
+  frontier: &Arc<(Mutex<Frontier>, Condvar)>,
+) {
   println!("Processing {} (depth: {})", url, current_depth);
 
   let (imgs, links) = match get_imgs_and_links(client, &url) {
     Ok((i, l)) => (i, l),
     Err(e) => {
       eprintln!("Error processing {url}: {e}");
-      return Ok(());
+      return;
     }
   };
 
-  for img_url in &imgs {
-    if let Err(e) = download_image(client, img_url, &settings.path) {
-      eprintln!("Failed to download {}: {}", img_url, e);
-      continue;
+  for img_url in imgs {
+    // The receiving end only disappears once every download worker has
+    // exited, which only happens after every page worker has exited, so
+    // this can't fail in practice; ignore a send error rather than
+    // aborting the crawl.
+    let _ = image_tx.send(img_url);
+  }
+
+  if !settings.recursive || current_depth >= settings.depth {
+    return;
+  }
+
+  let mut new_links = Vec::new();
+  {
+    let mut already_visited = already_visited.lock().unwrap();
+    for link in links {
+      if already_visited.insert(link.clone()) {
+        new_links.push(link);
+      }
     }
   }
 
-  if settings.recursive && current_depth < settings.depth {
-    process_links(client, links, settings, current_depth, already_visited)?;
+  if new_links.is_empty() {
+    return;
   }
 
-  Ok(())
+  let (lock, condvar) = &**frontier;
+  let mut state = lock.lock().unwrap();
+  state.queue.extend(new_links.into_iter().map(|link| (link, current_depth + 1)));
+  condvar.notify_all();
 }
 
 fn get_imgs_and_links(client: &ureq::Agent, url: &str) -> Result<(Vec<String>, Vec<String>), BoxedError> {
@@ -107,7 +240,7 @@ fn get_imgs_and_links(client: &ureq::Agent, url: &str) -> Result<(Vec<String>, V
       return Ok((Vec::new(), Vec::new()));
     }
     Err(_) => {
-      eprintln!("Unknown error"); 
+      eprintln!("Unknown error");
       return Ok((Vec::new(), Vec::new()));
     }
   };
@@ -117,7 +250,7 @@ fn get_imgs_and_links(client: &ureq::Agent, url: &str) -> Result<(Vec<String>, V
 
   let img_selector = Selector::parse("img").unwrap();
   let link_selector = Selector::parse("a").unwrap();
-  let valid_extensions = ["jpg", "jpeg", "png", "gif", "bmp"];
+  let valid_extensions = ["jpg", "jpeg", "png", "gif", "bmp", "heic", "heif"];
 
   let mut imgs = Vec::new();
   let mut links = Vec::new();
@@ -148,19 +281,25 @@ fn has_valid_extension(url: &str, valid_extensions: &[&str]) -> bool {
   false
 }
 
-fn download_image(client: &ureq::Agent, img_url: &str, base_path: &str) -> Result<(), BoxedError> {
+/// Downloads `img_url` and writes it under `base_path`, skipping the write
+/// if an image with the same SHA-256 content hash has already been saved by
+/// any worker (the same picture is often linked from many pages). The
+/// on-disk filename is sanitised so a crafted URL can't write outside
+/// `base_path`, and disambiguated on a basename collision so two distinct
+/// images never clobber each other.
+fn download_image(
+  client: &ureq::Agent,
+  img_url: &str,
+  base_path: &str,
+  seen_hashes: &Mutex<HashSet<String>>,
+) -> Result<(), BoxedError> {
 
   let parsed_url = Url::parse(img_url)?;
-  let filename = format!(
-    "{}/{}",
-    base_path.trim_end_matches('/'),
-    parsed_url.path().split('/').last().unwrap_or("unknown.jpg")
-  );
-
-  // Create dir
-  if let Some(parent) = Path::new(&filename).parent() {
-    fs::create_dir_all(parent)?;
-  }
+  let raw_name = parsed_url.path().rsplit('/').next().unwrap_or("");
+  let safe_name = safe_path::sanitize_filename(raw_name);
+
+  fs::create_dir_all(base_path)?;
+  let base_dir = Path::new(base_path).canonicalize()?;
 
   let mut bytes = Vec::new();
   client.get(img_url)
@@ -168,27 +307,24 @@ fn download_image(client: &ureq::Agent, img_url: &str, base_path: &str) -> Resul
     .into_reader()
     .read_to_end(&mut bytes)?;
 
-  std::fs::write(&filename, bytes)?;
+  let hash = sha256_hex(&bytes);
+  {
+    let mut seen_hashes = seen_hashes.lock().unwrap();
+    if !seen_hashes.insert(hash.clone()) {
+      println!("Skipping duplicate of an already-downloaded image: {}", img_url);
+      return Ok(());
+    }
+  }
 
-  println!("Downloaded: {}", filename);
+  let target_path = safe_path::write_collision_free(&base_dir, &safe_name, &hash, &bytes)?;
+
+  println!("Downloaded: {}", target_path.display());
   Ok(())
 }
 
-fn process_links (
-  client: &ureq::Agent,
-  links: Vec<String>,
-  settings: Settings,
-  current_depth: u32,
-  already_visited: HashSet<String>
-) ->Result<(), BoxedError> {
-
-  for link in links {
-    let settings = settings.clone();
-    let already_visited = already_visited.clone();
-    if let Err(e) = get_page_images(client, link, settings, current_depth + 1, already_visited) {
-      eprintln!("Error in recursive fetch: {}", e);
-    }
-  }
+fn sha256_hex(bytes: &[u8]) -> String {
+  let mut hasher = Sha256::new();
+  hasher.update(bytes);
+  hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
 
-  Ok(())
-}
\ No newline at end of file
@@ -0,0 +1,152 @@
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Windows reserved device names: writing to e.g. `CON` or `NUL` doesn't
+/// create a regular file, so they're rewritten like any other illegal name.
+const RESERVED_NAMES: &[&str] = &[
+  "CON", "PRN", "AUX", "NUL",
+  "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+  "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns an arbitrary (possibly attacker-controlled) URL path segment into a
+/// safe on-disk basename: directory components are dropped by only looking
+/// at the text after the last `/` or `\`, anything outside a conservative
+/// character set (including the `%` of a URL-encoded separator) is replaced,
+/// reserved names get a `_` prefix, and an empty result falls back to a
+/// fixed name; nothing is rejected outright.
+pub fn sanitize_filename(raw: &str) -> String {
+  let candidate = raw.rsplit(['/', '\\']).next().unwrap_or("").trim();
+
+  let cleaned: String = candidate.chars()
+    .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+    .collect();
+  let cleaned = cleaned.trim_start_matches('.');
+
+  if cleaned.is_empty() {
+    return "unknown.jpg".to_string();
+  }
+
+  let stem = cleaned.split('.').next().unwrap_or(cleaned);
+  if RESERVED_NAMES.contains(&stem.to_uppercase().as_str()) {
+    return format!("_{}", cleaned);
+  }
+
+  cleaned.to_string()
+}
+
+/// Joins `safe_name` under `base_dir` (already canonicalized) and writes
+/// `bytes` to it, appending a short content-hash suffix (and, if that's
+/// still taken, a numeric counter) on a basename collision so two distinct
+/// images never overwrite each other.
+///
+/// The allocate-a-free-name-and-create-it step is one atomic
+/// `create_new` open per candidate rather than a `exists()` check followed
+/// by a separate write, so two worker threads racing on the same basename
+/// can't both pass the check and have the second silently clobber the
+/// first.
+pub fn write_collision_free(base_dir: &Path, safe_name: &str, content_hash: &str, bytes: &[u8]) -> Result<PathBuf, Error> {
+  let candidate = base_dir.join(safe_name);
+  ensure_within(base_dir, &candidate)?;
+  if let Some(path) = try_create(&candidate, bytes)? {
+    return Ok(path);
+  }
+
+  let (stem, ext) = split_stem_ext(safe_name);
+  let short_hash = &content_hash[..content_hash.len().min(8)];
+
+  let candidate = base_dir.join(with_suffix(&stem, &ext, short_hash));
+  ensure_within(base_dir, &candidate)?;
+  if let Some(path) = try_create(&candidate, bytes)? {
+    return Ok(path);
+  }
+
+  let mut attempt = 1;
+  loop {
+    let candidate = base_dir.join(with_suffix(&stem, &ext, &format!("{}-{}", short_hash, attempt)));
+    ensure_within(base_dir, &candidate)?;
+    if let Some(path) = try_create(&candidate, bytes)? {
+      return Ok(path);
+    }
+    attempt += 1;
+  }
+}
+
+/// Atomically creates `path` if (and only if) it doesn't already exist and
+/// writes `bytes` to it, returning `Ok(None)` instead of an error on the one
+/// expected failure mode (something else already occupies that name).
+fn try_create(path: &Path, bytes: &[u8]) -> Result<Option<PathBuf>, Error> {
+  match OpenOptions::new().write(true).create_new(true).open(path) {
+    Ok(mut file) => {
+      file.write_all(bytes)?;
+      Ok(Some(path.to_path_buf()))
+    }
+    Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(None),
+    Err(e) => Err(e),
+  }
+}
+
+fn ensure_within(base_dir: &Path, candidate: &Path) -> Result<(), Error> {
+  if candidate.parent() != Some(base_dir) {
+    return Err(Error::new(ErrorKind::InvalidInput, "Sanitised path escaped the configured base directory"));
+  }
+  Ok(())
+}
+
+fn split_stem_ext(name: &str) -> (String, String) {
+  match name.rsplit_once('.') {
+    Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), ext.to_string()),
+    _ => (name.to_string(), String::new()),
+  }
+}
+
+fn with_suffix(stem: &str, ext: &str, suffix: &str) -> String {
+  if ext.is_empty() {
+    format!("{}_{}", stem, suffix)
+  } else {
+    format!("{}_{}.{}", stem, suffix, ext)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicU32, Ordering};
+
+  static TEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+  fn temp_base_dir() -> PathBuf {
+    let unique = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("spider_safe_path_{}_{}", std::process::id(), unique));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn sanitize_filename_strips_path_traversal() {
+    assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+    assert_eq!(sanitize_filename("..\\..\\windows\\system32\\config"), "config");
+    assert_eq!(sanitize_filename("/etc/passwd"), "passwd");
+  }
+
+  #[test]
+  fn sanitize_filename_prefixes_reserved_names() {
+    assert_eq!(sanitize_filename("CON"), "_CON");
+    assert_eq!(sanitize_filename("nul.jpg"), "_nul.jpg");
+  }
+
+  #[test]
+  fn write_collision_free_disambiguates_same_basename() {
+    let base_dir = temp_base_dir();
+
+    let first = write_collision_free(&base_dir, "photo.jpg", "aaaaaaaaaaaa", b"first").unwrap();
+    let second = write_collision_free(&base_dir, "photo.jpg", "bbbbbbbbbbbb", b"second").unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(std::fs::read(&first).unwrap(), b"first");
+    assert_eq!(std::fs::read(&second).unwrap(), b"second");
+
+    std::fs::remove_dir_all(&base_dir).unwrap();
+  }
+}
@@ -1,54 +1,173 @@
 use std::fs::File;
 use std::io::{Read, Write, Error, ErrorKind};
 
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+
+use crate::hotp::Algorithm;
+
+const HEADER_LEN: usize = 6;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// The HOTP parameters a key was enrolled with, read back from the plaintext
+/// header of its `.key` file so a SHA256/8-digit key verifies correctly even
+/// though it's unusual.
+pub struct KeyParams {
+  pub algorithm: Algorithm,
+  pub digits: u8,
+  pub period: u64,
+}
+
 pub struct KeyStorage {
   file_path: String,
-  encryption_key: Vec<u8>,
+  pin: String,
 }
 
 impl KeyStorage {
 
-  pub fn new(file_path: &str, encryption_key: &str) -> Self {
+  pub fn new(file_path: &str, pin: &str) -> Self {
     KeyStorage {
       file_path: file_path.to_string(),
-      encryption_key: encryption_key.as_bytes().to_vec(),
+      pin: pin.to_string(),
     }
   }
 
-  /// Encrypts or decrypts data
-  /// XOR is useful here because it can be used both ways, but is unsafe in real world apps
-  fn xor_encrypt_decrypt(&self, data: &[u8]) -> Vec<u8> {
-    data.iter()
-      .zip(self.encryption_key.iter().cycle())
-      .map(|(data_byte, key_byte)| data_byte ^ key_byte)
-      .collect()
+  /// Derives a 32-byte AES-256 key from the 4-digit PIN and a random salt.
+  /// PBKDF2 stretches the tiny PIN so the stored file isn't trivially
+  /// brute-forceable from the ciphertext alone.
+  fn derive_key(&self, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key_bytes = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(self.pin.as_bytes(), salt, PBKDF2_ROUNDS, &mut key_bytes);
+    Key::<Aes256Gcm>::from(key_bytes)
   }
 
-  pub fn store_key(&self, hex_key: &str) -> Result<(), Error> {
-    let key_bytes = self.hex_string_to_bytes(hex_key)?;
-    let encrypted = self.xor_encrypt_decrypt(&key_bytes);
-    
+  /// Writes `header || salt || nonce || ciphertext || tag` to disk, where
+  /// `header` is the plaintext algorithm/digits/period the key was enrolled
+  /// with. The header isn't secret, so it's stored outside the encrypted
+  /// part, but it's bound into the GCM associated data so tampering with it
+  /// (e.g. changing `digits` from 6 to 8) is caught as an authentication
+  /// failure rather than silently accepted. A header value outside the
+  /// accepted ranges is rejected by `decode_header` before decryption.
+  pub fn store_key(&self, seed_bytes: &[u8], params: &KeyParams) -> Result<(), Error> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let header = encode_header(params);
+    let cipher = Aes256Gcm::new(&self.derive_key(&salt));
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher.encrypt(nonce, Payload { msg: seed_bytes, aad: &header })
+      .map_err(|_| Error::new(ErrorKind::Other, "Failed to encrypt key"))?;
+
     let mut file = File::create(&self.file_path)?;
-    file.write_all(&encrypted)
+    file.write_all(&header)?;
+    file.write_all(&salt)?;
+    file.write_all(&nonce_bytes)?;
+    file.write_all(&ciphertext)
   }
 
-  pub fn read_key(&self) -> Result<Vec<u8>, Error> {
+  /// Reads back `header || salt || nonce || ciphertext || tag`, re-derives
+  /// the key from the PIN, and decrypts with the header as associated data.
+  /// An authentication failure means the PIN was wrong or either the header
+  /// or the ciphertext was tampered with, so we report it as one error.
+  pub fn read_key(&self) -> Result<(Vec<u8>, KeyParams), Error> {
     let mut file = File::open(&self.file_path)?;
-    let mut encrypted = Vec::new();
-    file.read_to_end(&mut encrypted)?;
-    
-    Ok(self.xor_encrypt_decrypt(&encrypted))
-  }
-
-  fn hex_string_to_bytes(&self, hex_string: &str) -> Result<Vec<u8>, Error> {
-    hex_string.chars()
-      .collect::<Vec<char>>()
-      .chunks(2)
-      .map(|chunk| {
-        let hex_pair: String = chunk.iter().collect();
-        u8::from_str_radix(&hex_pair, 16)
-          .map_err(|e| Error::new(ErrorKind::InvalidData, e))
-      })
-      .collect()
-  }
-}
\ No newline at end of file
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+
+    if content.len() < HEADER_LEN + SALT_LEN + NONCE_LEN + TAG_LEN {
+      return Err(Error::new(ErrorKind::InvalidData, "Key file is truncated or corrupted"));
+    }
+
+    let (header, rest) = content.split_at(HEADER_LEN);
+    let params = decode_header(header)?;
+
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(&self.derive_key(salt));
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    let seed_bytes = cipher.decrypt(nonce, Payload { msg: ciphertext, aad: header })
+      .map_err(|_| Error::new(ErrorKind::InvalidData, "wrong PIN or corrupted file"))?;
+
+    Ok((seed_bytes, params))
+  }
+}
+
+fn encode_header(params: &KeyParams) -> [u8; HEADER_LEN] {
+  let mut header = [0u8; HEADER_LEN];
+  header[0] = params.algorithm.id();
+  header[1] = params.digits;
+  header[2..6].copy_from_slice(&(params.period as u32).to_be_bytes());
+  header
+}
+
+fn decode_header(header: &[u8]) -> Result<KeyParams, Error> {
+  let algorithm = Algorithm::from_id(header[0])?;
+
+  let digits = header[1];
+  if !(6..=8).contains(&digits) {
+    return Err(Error::new(ErrorKind::InvalidData, "Key file header has an out-of-range digit count"));
+  }
+
+  let period = u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as u64;
+  if period == 0 {
+    return Err(Error::new(ErrorKind::InvalidData, "Key file header has a zero time step"));
+  }
+
+  Ok(KeyParams { algorithm, digits, period })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicU32, Ordering};
+
+  static TEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+  fn temp_key_path(name: &str) -> String {
+    let unique = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir()
+      .join(format!("ft_otp_{}_{}_{}.key", std::process::id(), unique, name))
+      .to_string_lossy()
+      .into_owned()
+  }
+
+  #[test]
+  fn store_and_read_round_trips() {
+    let path = temp_key_path("round_trip");
+    let params = KeyParams { algorithm: Algorithm::Sha256, digits: 8, period: 30 };
+    let seed = b"super-secret-seed-bytes".to_vec();
+
+    KeyStorage::new(&path, "1234").store_key(&seed, &params).unwrap();
+
+    let (read_seed, read_params) = KeyStorage::new(&path, "1234").read_key().unwrap();
+    assert_eq!(read_seed, seed);
+    assert_eq!(read_params.algorithm, Algorithm::Sha256);
+    assert_eq!(read_params.digits, 8);
+    assert_eq!(read_params.period, 30);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn wrong_pin_fails_with_clear_error() {
+    let path = temp_key_path("wrong_pin");
+    let params = KeyParams { algorithm: Algorithm::Sha1, digits: 6, period: 30 };
+
+    KeyStorage::new(&path, "1234").store_key(b"another-seed", &params).unwrap();
+
+    let err = KeyStorage::new(&path, "4321").read_key().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
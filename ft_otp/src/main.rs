@@ -1,17 +1,21 @@
 use std::{fs::File, io::Read, path::Path, time::SystemTime};
-use clap::{Parser, ArgGroup};
-use hmac::{Hmac, Mac};
-use sha1::Sha1;
+use clap::Parser;
+use clap::ArgGroup;
 
 mod key_storage;
-use key_storage::KeyStorage;
+use key_storage::{KeyParams, KeyStorage};
 
-const TOTP_PERIOD: u64 = 30;       // Time step in seconds
-const TOTP_DIGITS: usize = 6;      // Number of digits in the code
-const TOTP_MODULUS: i32 = 1000000; // 10^TOTP_DIGITS
+mod seed;
+mod otpauth;
+mod hotp;
+use hotp::Algorithm;
 
-const MIN_HEX_KEY_LENGTH: usize = 64;
-const MAX_HEX_KEY_LENGTH: usize = 160;
+const DEFAULT_PERIOD: u64 = 30;  // Time step in seconds
+const DEFAULT_DIGITS: u8 = 6;    // Number of digits in the code
+const DEFAULT_ALGORITHM: &str = "sha1";
+
+const DEFAULT_ISSUER: &str = "ft_otp";
+const DEFAULT_ACCOUNT: &str = "user";
 
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about = "ft_otp")]
@@ -32,101 +36,112 @@ struct Args {
     /// Path to an existing key file to generate a TOTP code
     #[arg(short = 'k', long, value_name = "PATH")]
     key_path: Option<String>,
+
+    /// Export the stored key as an otpauth:// URI and QR code instead of a TOTP code (requires -k)
+    #[arg(long, requires = "key_path")]
+    export: bool,
+
+    /// Account label to use when exporting, e.g. user@example.com (requires --export)
+    #[arg(long, value_name = "ACCOUNT", requires = "export")]
+    account: Option<String>,
+
+    /// Issuer label to use when exporting, e.g. the service name (requires --export)
+    #[arg(long, value_name = "ISSUER", requires = "export")]
+    issuer: Option<String>,
+
+    /// HMAC algorithm to enroll a new key with: sha1, sha256 or sha512 (requires -g)
+    #[arg(long, value_name = "ALGO", default_value = DEFAULT_ALGORITHM, requires = "generate_path")]
+    algorithm: String,
+
+    /// Number of digits in generated codes, 6-8 (requires -g)
+    #[arg(long, value_name = "DIGITS", default_value_t = DEFAULT_DIGITS, value_parser = clap::value_parser!(u8).range(6..=8), requires = "generate_path")]
+    digits: u8,
+
+    /// Time step in seconds between codes (requires -g)
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_PERIOD, value_parser = clap::value_parser!(u64).range(1..), requires = "generate_path")]
+    period: u64,
 }
 
 fn main() -> Result<(), std::io::Error> {
     let args = Args::parse();
 
     match args.generate_path {
-        Some(path) => generate_new_key(&path, &args.x),
-        _none => generate_totp_code(&args.key_path.unwrap(), &args.x),
+        Some(path) => generate_new_key(&path, &args.x, &args.algorithm, args.digits, args.period),
+        _none => {
+            let key_path = args.key_path.unwrap();
+            if args.export {
+                let issuer = args.issuer.as_deref().unwrap_or(DEFAULT_ISSUER);
+                let account = args.account.as_deref().unwrap_or(DEFAULT_ACCOUNT);
+                export_key(&key_path, &args.x, issuer, account)
+            } else {
+                generate_totp_code(&key_path, &args.x)
+            }
+        }
     }
 }
 
-fn generate_new_key(input_path: &str, encryption_key: &str) -> Result<(), std::io::Error> {
-    let hex_key = read_hex_key(input_path)?;
-    validate_hex_key(&hex_key)?;
+fn generate_new_key(
+    input_path: &str,
+    encryption_key: &str,
+    algorithm: &str,
+    digits: u8,
+    period: u64,
+) -> Result<(), std::io::Error> {
+    let seed_string = read_seed_string(input_path)?;
+    let seed_bytes = seed::decode_seed(&seed_string)?;
+
+    let algorithm = algorithm.parse::<Algorithm>()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
 
     let output_path = Path::new(input_path)
         .with_extension("key")
         .to_string_lossy()
         .into_owned();
-    
+
     let storage = KeyStorage::new(&output_path, encryption_key);
-    storage.store_key(&hex_key)?;
+    storage.store_key(&seed_bytes, &KeyParams { algorithm, digits, period })?;
     println!("Key saved successfully.");
     Ok(())
 }
 
+fn export_key(key_path: &str, encryption_key: &str, issuer: &str, account: &str) -> Result<(), std::io::Error> {
+    let storage = KeyStorage::new(key_path, encryption_key);
+    let (seed_bytes, params) = storage.read_key()?;
+
+    let uri = otpauth::build_uri(&seed_bytes, issuer, account, &params);
+    println!("{}", uri);
+    println!();
+    otpauth::print_qr(&uri);
+
+    Ok(())
+}
+
 fn generate_totp_code(key_path: &str, encryption_key: &str) -> Result<(), std::io::Error> {
     let storage = KeyStorage::new(key_path, encryption_key);
-    let key = storage.read_key()?;
+    let (key, params) = storage.read_key()?;
 
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .expect("System time before Unix epoch")
         .as_secs();
-    
-    let time_counter = current_time / TOTP_PERIOD;
-    let code = generate_hotp(key, time_counter, TOTP_MODULUS);
-    
+
+    let time_counter = current_time / params.period;
+    let code = hotp::generate_hotp(&key, time_counter, params.digits, params.algorithm);
+
     println!("{}", code);
     Ok(())
 }
 
-/// Follows RFC 4226's requirements
-fn generate_hotp(secret_key: Vec<u8>, counter: u64, modulus: i32) -> String {
-    // Convert counter to big-endian byte array
-    let counter_bytes = counter.to_be_bytes();
-
-    // Generate HMAC-SHA1
-    let mut mac = Hmac::<Sha1>::new_from_slice(&secret_key)
-        .expect("HMAC can take key of any size");
-    mac.update(&counter_bytes);
-    let hmac_result = mac.finalize().into_bytes();
-
-    // Dynamic truncation
-    let offset = (hmac_result[19] & 0x0F) as usize;
-    let truncated = ((hmac_result[offset] & 0x7F) as i32) << 24 |
-                   ((hmac_result[offset + 1] & 0xFF) as i32) << 16 |
-                   ((hmac_result[offset + 2] & 0xFF) as i32) << 8 |
-                   (hmac_result[offset + 3] & 0xFF) as i32;
-
-    // Generate fixed-length code
-    format!("{:0width$}", truncated % modulus, width = TOTP_DIGITS)
-}
-
-fn read_hex_key(path: &str) -> Result<String, std::io::Error> {
+fn read_seed_string(path: &str) -> Result<String, std::io::Error> {
     let mut file = File::open(path)?;
     let mut content = Vec::new();
     file.read_to_end(&mut content)?;
     Ok(String::from_utf8_lossy(&content).into_owned())
 }
 
-fn validate_hex_key(key: &str) -> Result<(), std::io::Error> {
-    use std::io::{Error, ErrorKind};
-
-    if key.len() < MIN_HEX_KEY_LENGTH || key.len() > MAX_HEX_KEY_LENGTH {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            format!("Key length must be between {} and {} characters", 
-                   MIN_HEX_KEY_LENGTH, MAX_HEX_KEY_LENGTH)
-        ));
-    }
-
-    if !key.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            "Key must contain only hexadecimal digits"
-        ));
-    }
-
-    Ok(())
-}
-
 fn validate_encryption_key(input: &str) -> Result<String, String> {
     if input.len() != 4 || !input.chars().all(|c| c.is_ascii_digit()) {
         return Err("Encryption key must be exactly 4 digits".to_string());
     }
     Ok(input.to_string())
-}
\ No newline at end of file
+}
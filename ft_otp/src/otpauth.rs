@@ -0,0 +1,67 @@
+use qrcode::render::unicode;
+use qrcode::QrCode;
+
+use crate::key_storage::KeyParams;
+use crate::seed;
+
+/// Builds the `otpauth://totp/...` provisioning URI for `seed_bytes`, following
+/// the Google Authenticator Key URI Format so phone apps can enroll it directly.
+pub fn build_uri(seed_bytes: &[u8], issuer: &str, account: &str, params: &KeyParams) -> String {
+  let secret = seed::encode_base32(seed_bytes);
+  let label = format!("{}:{}", percent_encode(issuer), percent_encode(account));
+
+  format!(
+    "otpauth://totp/{label}?secret={secret}&issuer={issuer}&period={period}&digits={digits}&algorithm={algorithm}",
+    label = label,
+    secret = secret,
+    issuer = percent_encode(issuer),
+    period = params.period,
+    digits = params.digits,
+    algorithm = params.algorithm.as_str(),
+  )
+}
+
+/// Renders `uri` as an ASCII/Unicode-block QR code in the terminal.
+pub fn print_qr(uri: &str) {
+  match QrCode::new(uri.as_bytes()) {
+    Ok(code) => {
+      let image = code.render::<unicode::Dense1x2>()
+        .quiet_zone(false)
+        .build();
+      println!("{}", image);
+    }
+    Err(e) => eprintln!("Failed to render QR code: {}", e),
+  }
+}
+
+/// Percent-encodes the label/issuer text for use inside the URI, since account
+/// and issuer names may contain spaces, colons or other reserved characters.
+fn percent_encode(input: &str) -> String {
+  let mut out = String::with_capacity(input.len());
+
+  for byte in input.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+      _ => out.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hotp::Algorithm;
+
+  #[test]
+  fn build_uri_matches_the_google_authenticator_key_uri_format() {
+    let params = KeyParams { algorithm: Algorithm::Sha1, digits: 6, period: 30 };
+    let uri = build_uri(&[0u8], "My Co", "user@example.com", &params);
+
+    assert_eq!(
+      uri,
+      "otpauth://totp/My%20Co:user%40example.com?secret=AA&issuer=My%20Co&period=30&digits=6&algorithm=SHA1"
+    );
+  }
+}
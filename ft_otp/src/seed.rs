@@ -0,0 +1,85 @@
+use std::io::{Error, ErrorKind};
+
+pub const MIN_HEX_KEY_LENGTH: usize = 64;
+pub const MAX_HEX_KEY_LENGTH: usize = 160;
+
+/// Decodes a seed given as hex or RFC 4648 Base32, auto-detecting the format.
+/// Hex only ever uses `[0-9a-fA-F]`; Base32 uses `A-Z`/`2-7` with optional `=`
+/// padding, so any character outside the hex alphabet means it's Base32.
+pub fn decode_seed(input: &str) -> Result<Vec<u8>, Error> {
+  let trimmed = input.trim();
+
+  if is_hex(trimmed) {
+    validate_hex_key(trimmed)?;
+    hex_to_bytes(trimmed)
+  } else {
+    decode_base32(trimmed)
+  }
+}
+
+/// Re-encodes raw seed bytes as unpadded Base32, the form `otpauth://` URIs use.
+pub fn encode_base32(bytes: &[u8]) -> String {
+  base32::encode(base32::Alphabet::RFC4648 { padding: false }, bytes)
+}
+
+fn is_hex(s: &str) -> bool {
+  !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn validate_hex_key(key: &str) -> Result<(), Error> {
+  if key.len() < MIN_HEX_KEY_LENGTH || key.len() > MAX_HEX_KEY_LENGTH {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      format!("Key length must be between {} and {} characters",
+             MIN_HEX_KEY_LENGTH, MAX_HEX_KEY_LENGTH)
+    ));
+  }
+
+  Ok(())
+}
+
+fn hex_to_bytes(hex_string: &str) -> Result<Vec<u8>, Error> {
+  hex_string.chars()
+    .collect::<Vec<char>>()
+    .chunks(2)
+    .map(|chunk| {
+      let hex_pair: String = chunk.iter().collect();
+      u8::from_str_radix(&hex_pair, 16)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    })
+    .collect()
+}
+
+fn decode_base32(input: &str) -> Result<Vec<u8>, Error> {
+  let upper = input.trim_end_matches('=').to_uppercase();
+
+  if upper.is_empty() || !upper.chars().all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c)) {
+    return Err(Error::new(ErrorKind::InvalidInput, "Key must be hex or RFC 4648 Base32"));
+  }
+
+  base32::decode(base32::Alphabet::RFC4648 { padding: false }, &upper)
+    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid Base32 seed"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decode_seed_accepts_hex() {
+    let hex = "ab".repeat(32); // 64 hex chars, the minimum key length
+    assert_eq!(decode_seed(&hex).unwrap(), vec![0xabu8; 32]);
+  }
+
+  #[test]
+  fn decode_seed_accepts_base32() {
+    let bytes = vec![0xabu8; 32];
+    let encoded = encode_base32(&bytes);
+    assert_eq!(decode_seed(&encoded).unwrap(), bytes);
+  }
+
+  #[test]
+  fn decode_seed_rejects_input_that_is_neither_hex_nor_base32() {
+    assert!(decode_seed("!!!not-valid!!!").is_err());
+  }
+}